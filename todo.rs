@@ -5,7 +5,7 @@
 //! license: MIT
 //!
 //! A simple, cross-platform task manager in one file.
-//! Stores tasks in JSON under your OS’s data directory.
+//! Stores tasks in JSON or SQLite under your OS’s data directory.
 //!
 //! # Usage
 //! ```bash
@@ -23,6 +23,33 @@
 //!
 //! # Clear all tasks
 //! todo clear
+//!
+//! # Add a high-priority task, or re-prioritize one later
+//! todo add --priority high "Ship the release"
+//! todo priority 3 low
+//!
+//! # Tag a task with a project and later filter by it
+//! todo add --project work "Review PR #42"
+//! todo list --project work
+//!
+//! # Set a due date and review tasks by age or deadline
+//! todo add --due 2024-12-31 "File taxes"
+//! todo due 3 2024-12-31
+//! todo list --sort due
+//!
+//! # Use the SQLite backend instead of the default JSON file
+//! todo --backend sqlite list
+//!
+//! # Copy existing JSON tasks into the SQLite store
+//! todo migrate
+//!
+//! # Move done tasks out of the way, then bring one back
+//! todo archive
+//! todo list --archived
+//! todo restore 3
+//!
+//! # Generate a shell completion script
+//! todo completions zsh > _todo
 //! ```
 //!
 //! # Build & Run
@@ -38,139 +65,911 @@
 //! serde = { version = "1.0", features = ["derive"] }
 //! serde_json = "1.0"
 //! directories = "4.4"
+//! rusqlite = { version = "0.31", features = ["bundled"] }
+//! time = { version = "0.3", features = ["formatting", "parsing", "macros", "serde", "serde-well-known"] }
+//! clap_complete = "4.3"
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
 use directories::ProjectDirs;
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use std::{
+    env,
     fs::{self, File},
     io::{self, BufReader, Write},
     path::PathBuf,
     process::exit,
 };
+use time::{
+    format_description::{well_known::Rfc3339, FormatItem},
+    macros::format_description,
+    Date, OffsetDateTime,
+};
 
 /// CLI definition
 #[derive(Parser)]
 #[command(name = "todo", about = "Simple ToDo CLI in Rust")]
 struct Cli {
+    /// Storage backend to use (defaults to the `TODO_BACKEND` env var, then `json`)
+    #[arg(long, value_enum, global = true)]
+    backend: Option<Backend>,
+
     #[command(subcommand)]
     cmd: Commands,
 }
 
+/// Which storage backend to read and write tasks through
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Backend {
+    Json,
+    Sqlite,
+}
+
+impl Backend {
+    /// Resolve the backend from the CLI flag, falling back to `TODO_BACKEND`, then `json`
+    fn resolve(flag: Option<Backend>) -> Backend {
+        if let Some(b) = flag {
+            return b;
+        }
+        match env::var("TODO_BACKEND").ok().as_deref() {
+            Some("sqlite") => Backend::Sqlite,
+            _ => Backend::Json,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Add a new task
-    Add { description: Vec<String> },
+    Add {
+        description: Vec<String>,
+        /// Priority of the new task
+        #[arg(long, value_enum, default_value_t = Priority::Normal)]
+        priority: Priority,
+        /// Project or group this task belongs to
+        #[arg(long)]
+        project: Option<String>,
+        /// Related URL (issue, PR, document, ...)
+        #[arg(long)]
+        link: Option<String>,
+        /// Due date in YYYY-MM-DD format
+        #[arg(long, value_parser = parse_date)]
+        due: Option<Date>,
+    },
     /// List all tasks
-    List,
+    List {
+        /// Only show tasks belonging to this project
+        #[arg(long)]
+        project: Option<String>,
+        /// Sort by task age or due date instead of priority
+        #[arg(long, value_enum)]
+        sort: Option<SortKey>,
+        /// Show archived tasks instead of active ones
+        #[arg(long)]
+        archived: bool,
+    },
     /// Mark a task done
     Done { id: usize },
     /// Remove a task
     Remove { id: usize },
     /// Clear all tasks
     Clear,
+    /// Move all done tasks out of the active list into the archive
+    Archive,
+    /// Move a task back from the archive into the active list
+    Restore { id: usize },
+    /// Set or change a task's due date
+    Due {
+        id: usize,
+        #[arg(value_parser = parse_date)]
+        date: Date,
+    },
+    /// Change the priority of an existing task
+    Priority {
+        id: usize,
+        #[arg(value_enum)]
+        level: Priority,
+    },
+    /// Copy all tasks from the JSON store into the SQLite store
+    Migrate,
+    /// Generate a shell completion script on stdout
+    Completions { shell: Shell },
+}
+
+/// Alternate `list` orderings selectable with `--sort`
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SortKey {
+    /// Oldest tasks first
+    Age,
+    /// Earliest due date first; tasks without a due date sort last
+    Due,
+}
+
+/// How urgently a task should be worked on; higher priorities sort first in `list`
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl std::fmt::Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Priority::Low => write!(f, "low"),
+            Priority::Normal => write!(f, "normal"),
+            Priority::High => write!(f, "high"),
+        }
+    }
 }
 
 /// A single task record
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 struct Task {
     id: usize,
     description: String,
     done: bool,
+    #[serde(default)]
+    priority: Priority,
+    #[serde(default)]
+    project: Option<String>,
+    #[serde(default)]
+    link: Option<String>,
+    #[serde(default = "OffsetDateTime::now_utc", with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    done_at: Option<OffsetDateTime>,
+    #[serde(default, with = "due_date")]
+    due: Option<Date>,
+}
+
+/// `YYYY-MM-DD` format used for `due` dates, both on the CLI and in storage
+const DATE_FORMAT: &[FormatItem<'_>] = format_description!("[year]-[month]-[day]");
+
+/// Parse a `--due`/`todo due` CLI argument as a calendar date
+fn parse_date(s: &str) -> Result<Date, String> {
+    Date::parse(s, DATE_FORMAT).map_err(|e| format!("invalid date '{}': {}", s, e))
 }
 
-/// Return path to tasks.json in OS‐specific data directory
-fn data_file() -> io::Result<PathBuf> {
+/// Whether a task is unfinished and past its due date
+fn is_overdue(t: &Task) -> bool {
+    match t.due {
+        Some(due) if !t.done => due < OffsetDateTime::now_utc().date(),
+        _ => false,
+    }
+}
+
+/// `serde` support for `Option<Date>` using the same `YYYY-MM-DD` format as the CLI
+mod due_date {
+    use super::{Date, DATE_FORMAT};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(date: &Option<Date>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match date {
+            Some(d) => serializer.serialize_some(&d.format(DATE_FORMAT).map_err(serde::ser::Error::custom)?),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Date>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw: Option<String> = Option::deserialize(deserializer)?;
+        raw.map(|s| Date::parse(&s, DATE_FORMAT).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+impl Ord for Task {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other
+            .priority
+            .cmp(&self.priority)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Task {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Fields supplied when creating a new task
+struct InsertTaskData {
+    description: String,
+    priority: Priority,
+    project: Option<String>,
+    link: Option<String>,
+    due: Option<Date>,
+}
+
+/// Fields that can be changed on an existing task
+#[derive(Default)]
+struct UpdateTaskData {
+    done: Option<bool>,
+    priority: Option<Priority>,
+    due: Option<Date>,
+}
+
+/// Storage abstraction implemented by each backend (JSON file, SQLite, ...)
+trait Repository {
+    /// Return every stored task
+    fn all(&self) -> io::Result<Vec<Task>>;
+    /// Create a task and return it with its assigned id
+    fn insert(&mut self, data: InsertTaskData) -> io::Result<Task>;
+    /// Apply a partial update to the task with the given id
+    fn update(&mut self, id: usize, data: UpdateTaskData) -> io::Result<bool>;
+    /// Delete the task with the given id, returning whether it existed
+    fn remove(&mut self, id: usize) -> io::Result<bool>;
+    /// Delete every task
+    fn clear(&mut self) -> io::Result<()>;
+    /// Insert a task exactly as given, preserving its id and timestamps; used to move a task
+    /// between the active and archive stores without changing its identity
+    fn insert_existing(&mut self, task: Task) -> io::Result<()>;
+}
+
+/// Return path to a named file in the OS‐specific data directory
+fn data_file(name: &str) -> io::Result<PathBuf> {
     let proj = ProjectDirs::from("com", "bocaletto-luca", "todo")
-        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Cannot determine data directory"))?;
+        .ok_or_else(|| io::Error::other("Cannot determine data directory"))?;
     let dir = proj.data_dir();
     fs::create_dir_all(dir)?;
-    Ok(dir.join("tasks.json"))
+    Ok(dir.join(name))
 }
 
-/// Load task list or return empty
-fn load_tasks() -> io::Result<Vec<Task>> {
-    let path = data_file()?;
-    if !path.exists() {
-        return Ok(Vec::new());
-    }
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let tasks = serde_json::from_reader(reader)?;
-    Ok(tasks)
+/// Allocate the next globally unique task id. Backed by a persisted counter so ids stay unique
+/// across the active and archive stores even after one of them is emptied out (its own
+/// `MAX(id)` would otherwise restart from zero and collide with an id already handed out).
+/// `known_max` is the highest id the caller has seen in its own store, folded in so the counter
+/// also advances past ids written before this counter file existed.
+fn next_task_id(known_max: usize) -> io::Result<usize> {
+    let path = data_file("id_counter")?;
+    let stored: usize = if path.exists() {
+        fs::read_to_string(&path)?.trim().parse().unwrap_or(0)
+    } else {
+        0
+    };
+    let next = stored.max(known_max) + 1;
+    fs::write(&path, next.to_string())?;
+    Ok(next)
 }
 
-/// Save task list to JSON
-fn save_tasks(tasks: &[Task]) -> io::Result<()> {
-    let path = data_file()?;
-    let mut file = File::create(path)?;
-    let data = serde_json::to_string_pretty(tasks)?;
-    file.write_all(data.as_bytes())?;
+/// Advance the persisted id counter so it never hands out an id at or below one already in use,
+/// e.g. after `insert_existing` brings a task with a specific id back into a store.
+fn bump_task_id_counter(at_least: usize) -> io::Result<()> {
+    let path = data_file("id_counter")?;
+    let stored: usize = if path.exists() {
+        fs::read_to_string(&path)?.trim().parse().unwrap_or(0)
+    } else {
+        0
+    };
+    if at_least > stored {
+        fs::write(&path, at_least.to_string())?;
+    }
     Ok(())
 }
 
-/// Generate next unique task ID
-fn next_id(tasks: &[Task]) -> usize {
-    tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1
+/// JSON-file-backed repository: the whole task list is rewritten on each mutation
+struct JsonRepository {
+    path: PathBuf,
+    tasks: Vec<Task>,
+}
+
+impl JsonRepository {
+    fn open(filename: &str) -> io::Result<Self> {
+        let path = data_file(filename)?;
+        let tasks = if path.exists() {
+            let file = File::open(&path)?;
+            serde_json::from_reader(BufReader::new(file))?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { path, tasks })
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let mut file = File::create(&self.path)?;
+        let data = serde_json::to_string_pretty(&self.tasks)?;
+        file.write_all(data.as_bytes())
+    }
+}
+
+impl Repository for JsonRepository {
+    fn all(&self) -> io::Result<Vec<Task>> {
+        Ok(self.tasks.clone())
+    }
+
+    fn insert(&mut self, data: InsertTaskData) -> io::Result<Task> {
+        let known_max = self.tasks.iter().map(|t| t.id).max().unwrap_or(0);
+        let task = Task {
+            id: next_task_id(known_max)?,
+            description: data.description,
+            done: false,
+            priority: data.priority,
+            project: data.project,
+            link: data.link,
+            created_at: OffsetDateTime::now_utc(),
+            done_at: None,
+            due: data.due,
+        };
+        self.tasks.push(task.clone());
+        self.save()?;
+        Ok(task)
+    }
+
+    fn update(&mut self, id: usize, data: UpdateTaskData) -> io::Result<bool> {
+        let Some(t) = self.tasks.iter_mut().find(|t| t.id == id) else {
+            return Ok(false);
+        };
+        if let Some(done) = data.done {
+            t.done = done;
+            if done {
+                t.done_at = Some(OffsetDateTime::now_utc());
+            }
+        }
+        if let Some(due) = data.due {
+            t.due = Some(due);
+        }
+        if let Some(priority) = data.priority {
+            t.priority = priority;
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    fn remove(&mut self, id: usize) -> io::Result<bool> {
+        let original = self.tasks.len();
+        self.tasks.retain(|t| t.id != id);
+        let removed = self.tasks.len() < original;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.tasks.clear();
+        self.save()
+    }
+
+    fn insert_existing(&mut self, task: Task) -> io::Result<()> {
+        bump_task_id_counter(task.id)?;
+        self.tasks.retain(|t| t.id != task.id);
+        self.tasks.push(task);
+        self.save()
+    }
+}
+
+/// SQLite-backed repository, one row per task in the named table
+struct SqliteRepository {
+    conn: Connection,
+    table: &'static str,
+}
+
+impl SqliteRepository {
+    fn open(table: &'static str) -> io::Result<Self> {
+        let path = data_file("tasks.db")?;
+        let conn = Connection::open(path).map_err(to_io_error)?;
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    id          INTEGER PRIMARY KEY,
+                    description TEXT NOT NULL,
+                    done        INTEGER NOT NULL,
+                    priority    INTEGER NOT NULL DEFAULT 1,
+                    project     TEXT,
+                    link        TEXT,
+                    created_at  TEXT NOT NULL,
+                    done_at     TEXT,
+                    due         TEXT
+                )",
+                table
+            ),
+            (),
+        )
+        .map_err(to_io_error)?;
+        Ok(Self { conn, table })
+    }
+}
+
+/// Encode a `Priority` the same way its `Ord` impl ranks it: `Low` < `Normal` < `High`
+fn priority_to_i64(priority: Priority) -> i64 {
+    match priority {
+        Priority::Low => 0,
+        Priority::Normal => 1,
+        Priority::High => 2,
+    }
+}
+
+fn priority_from_i64(value: i64) -> Priority {
+    match value {
+        0 => Priority::Low,
+        2 => Priority::High,
+        _ => Priority::Normal,
+    }
+}
+
+fn format_rfc3339(dt: OffsetDateTime) -> io::Result<String> {
+    dt.format(&Rfc3339).map_err(io::Error::other)
+}
+
+fn parse_rfc3339(s: &str) -> io::Result<OffsetDateTime> {
+    OffsetDateTime::parse(s, &Rfc3339).map_err(io::Error::other)
+}
+
+fn format_due(due: Option<Date>) -> io::Result<Option<String>> {
+    due.map(|d| d.format(DATE_FORMAT).map_err(io::Error::other)).transpose()
+}
+
+fn parse_due(s: Option<String>) -> io::Result<Option<Date>> {
+    s.map(|s| Date::parse(&s, DATE_FORMAT).map_err(io::Error::other))
+        .transpose()
+}
+
+impl Repository for SqliteRepository {
+    fn all(&self) -> io::Result<Vec<Task>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT id, description, done, priority, project, link, created_at, done_at, due \
+                 FROM {} ORDER BY id",
+                self.table
+            ))
+            .map_err(to_io_error)?;
+        let rows = stmt
+            .query_map((), |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as usize,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)? != 0,
+                    priority_from_i64(row.get::<_, i64>(3)?),
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, String>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                    row.get::<_, Option<String>>(8)?,
+                ))
+            })
+            .map_err(to_io_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(to_io_error)?;
+
+        rows.into_iter()
+            .map(
+                |(id, description, done, priority, project, link, created_at, done_at, due)| {
+                    Ok(Task {
+                        id,
+                        description,
+                        done,
+                        priority,
+                        project,
+                        link,
+                        created_at: parse_rfc3339(&created_at)?,
+                        done_at: done_at.map(|s| parse_rfc3339(&s)).transpose()?,
+                        due: parse_due(due)?,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    fn insert(&mut self, data: InsertTaskData) -> io::Result<Task> {
+        let known_max: i64 = self
+            .conn
+            .query_row(
+                &format!("SELECT COALESCE(MAX(id), 0) FROM {}", self.table),
+                (),
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(to_io_error)?;
+        let id = next_task_id(known_max as usize)? as i64;
+        let created_at = OffsetDateTime::now_utc();
+        self.conn
+            .execute(
+                &format!(
+                    "INSERT INTO {} (id, description, done, priority, project, link, created_at, done_at, due) \
+                     VALUES (?1, ?2, 0, ?3, ?4, ?5, ?6, NULL, ?7)",
+                    self.table
+                ),
+                (
+                    id,
+                    &data.description,
+                    priority_to_i64(data.priority),
+                    &data.project,
+                    &data.link,
+                    format_rfc3339(created_at)?,
+                    format_due(data.due)?,
+                ),
+            )
+            .map_err(to_io_error)?;
+        Ok(Task {
+            id: id as usize,
+            description: data.description,
+            done: false,
+            priority: data.priority,
+            project: data.project,
+            link: data.link,
+            created_at,
+            done_at: None,
+            due: data.due,
+        })
+    }
+
+    fn update(&mut self, id: usize, data: UpdateTaskData) -> io::Result<bool> {
+        let mut changed = false;
+        if let Some(done) = data.done {
+            changed |= self
+                .conn
+                .execute(
+                    &format!("UPDATE {} SET done = ?1 WHERE id = ?2", self.table),
+                    (done as i64, id as i64),
+                )
+                .map_err(to_io_error)?
+                > 0;
+            if done {
+                self.conn
+                    .execute(
+                        &format!("UPDATE {} SET done_at = ?1 WHERE id = ?2", self.table),
+                        (format_rfc3339(OffsetDateTime::now_utc())?, id as i64),
+                    )
+                    .map_err(to_io_error)?;
+            }
+        }
+        if let Some(priority) = data.priority {
+            changed |= self
+                .conn
+                .execute(
+                    &format!("UPDATE {} SET priority = ?1 WHERE id = ?2", self.table),
+                    (priority_to_i64(priority), id as i64),
+                )
+                .map_err(to_io_error)?
+                > 0;
+        }
+        if let Some(due) = data.due {
+            changed |= self
+                .conn
+                .execute(
+                    &format!("UPDATE {} SET due = ?1 WHERE id = ?2", self.table),
+                    (due.format(DATE_FORMAT).map_err(io::Error::other)?, id as i64),
+                )
+                .map_err(to_io_error)?
+                > 0;
+        }
+        if data.done.is_none() && data.priority.is_none() && data.due.is_none() {
+            return Ok(true);
+        }
+        Ok(changed)
+    }
+
+    fn remove(&mut self, id: usize) -> io::Result<bool> {
+        let changed = self
+            .conn
+            .execute(
+                &format!("DELETE FROM {} WHERE id = ?1", self.table),
+                (id as i64,),
+            )
+            .map_err(to_io_error)?;
+        Ok(changed > 0)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.conn
+            .execute(&format!("DELETE FROM {}", self.table), ())
+            .map_err(to_io_error)?;
+        Ok(())
+    }
+
+    fn insert_existing(&mut self, task: Task) -> io::Result<()> {
+        bump_task_id_counter(task.id)?;
+        self.conn
+            .execute(
+                &format!("DELETE FROM {} WHERE id = ?1", self.table),
+                (task.id as i64,),
+            )
+            .map_err(to_io_error)?;
+        self.conn
+            .execute(
+                &format!(
+                    "INSERT INTO {} (id, description, done, priority, project, link, created_at, done_at, due) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    self.table
+                ),
+                (
+                    task.id as i64,
+                    &task.description,
+                    task.done as i64,
+                    priority_to_i64(task.priority),
+                    &task.project,
+                    &task.link,
+                    format_rfc3339(task.created_at)?,
+                    task.done_at.map(format_rfc3339).transpose()?,
+                    format_due(task.due)?,
+                ),
+            )
+            .map_err(to_io_error)?;
+        Ok(())
+    }
+}
+
+/// Wrap a `rusqlite::Error` as an `io::Error` so both backends share one error type
+fn to_io_error(e: rusqlite::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+/// Open the named store ("tasks" for the active list, "finished" for the archive) for the
+/// selected backend
+fn open_repository(backend: Backend, store: &'static str) -> io::Result<Box<dyn Repository>> {
+    match backend {
+        Backend::Json => Ok(Box::new(JsonRepository::open(&format!("{}.json", store))?)),
+        Backend::Sqlite => Ok(Box::new(SqliteRepository::open(store)?)),
+    }
+}
+
+/// Print one task line, tagging it with its project and an overdue badge when applicable
+fn print_task(t: &Task) {
+    let mark = if t.done { "[x]" } else { "[ ]" };
+    let mut line = format!("{} {} {}: {}", mark, t.priority, t.id, t.description);
+    if let Some(project) = &t.project {
+        line.push_str(&format!(" ({})", project));
+    }
+    if let Some(link) = &t.link {
+        line.push_str(&format!(" <{}>", link));
+    }
+    if is_overdue(t) {
+        line.push_str(" [!overdue]");
+    }
+    println!("{}", line);
+}
+
+/// Sort tasks in place according to `list --sort`, defaulting to priority order
+fn sort_tasks(tasks: &mut [Task], sort: Option<SortKey>) {
+    match sort {
+        Some(SortKey::Age) => tasks.sort_by_key(|t| t.created_at),
+        Some(SortKey::Due) => tasks.sort_by(|a, b| match (a.due, b.due) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        }),
+        None => tasks.sort(),
+    }
+}
+
+/// Print tasks grouped under a header per project, untagged tasks under "(no project)"
+fn print_grouped_by_project(tasks: &[Task]) {
+    let mut projects: Vec<&str> = tasks
+        .iter()
+        .filter_map(|t| t.project.as_deref())
+        .collect();
+    projects.sort_unstable();
+    projects.dedup();
+
+    for project in projects {
+        println!("# {}", project);
+        for t in tasks.iter().filter(|t| t.project.as_deref() == Some(project)) {
+            print_task(t);
+        }
+    }
+
+    let untagged: Vec<&Task> = tasks.iter().filter(|t| t.project.is_none()).collect();
+    if !untagged.is_empty() {
+        println!("# (no project)");
+        for t in untagged {
+            print_task(t);
+        }
+    }
 }
 
 fn main() {
     let cli = Cli::parse();
-    let mut tasks = match load_tasks() {
-        Ok(v) => v,
+    let backend = Backend::resolve(cli.backend);
+
+    if let Commands::Completions { shell } = cli.cmd {
+        clap_complete::generate(shell, &mut Cli::command(), "todo", &mut io::stdout());
+        return;
+    }
+
+    if let Commands::Migrate = cli.cmd {
+        let result = (|| -> Result<(), String> {
+            let json_repo =
+                JsonRepository::open("tasks.json").map_err(|e| format!("JSON load error: {}", e))?;
+            let mut sqlite_repo =
+                SqliteRepository::open("tasks").map_err(|e| format!("SQLite open error: {}", e))?;
+            let tasks = json_repo.all().map_err(|e| format!("JSON read error: {}", e))?;
+            let count = tasks.len();
+            for t in tasks {
+                sqlite_repo
+                    .insert_existing(t)
+                    .map_err(|e| format!("SQLite insert error: {}", e))?;
+            }
+            println!("[✓] Migrated {} task(s) from JSON into SQLite.", count);
+            Ok(())
+        })();
+        if let Err(msg) = result {
+            eprintln!("Error: {}", msg);
+            exit(1);
+        }
+        return;
+    }
+
+    if let Commands::Archive = cli.cmd {
+        let result = (|| -> Result<(), String> {
+            let mut active =
+                open_repository(backend, "tasks").map_err(|e| format!("Open error: {}", e))?;
+            let mut archive =
+                open_repository(backend, "finished").map_err(|e| format!("Open error: {}", e))?;
+            let done: Vec<Task> = active
+                .all()
+                .map_err(|e| format!("Load error: {}", e))?
+                .into_iter()
+                .filter(|t| t.done)
+                .collect();
+            let count = done.len();
+            for t in done {
+                archive
+                    .insert_existing(t.clone())
+                    .map_err(|e| format!("Archive error: {}", e))?;
+                active
+                    .remove(t.id)
+                    .map_err(|e| format!("Save error: {}", e))?;
+            }
+            println!("[✓] Archived {} done task(s).", count);
+            Ok(())
+        })();
+        if let Err(msg) = result {
+            eprintln!("Error: {}", msg);
+            exit(1);
+        }
+        return;
+    }
+
+    if let Commands::Restore { id } = cli.cmd {
+        let result = (|| -> Result<(), String> {
+            let mut archive =
+                open_repository(backend, "finished").map_err(|e| format!("Open error: {}", e))?;
+            let mut active =
+                open_repository(backend, "tasks").map_err(|e| format!("Open error: {}", e))?;
+            let task = archive
+                .all()
+                .map_err(|e| format!("Load error: {}", e))?
+                .into_iter()
+                .find(|t| t.id == id)
+                .ok_or_else(|| format!("Task #{} not found in archive.", id))?;
+            active
+                .insert_existing(task)
+                .map_err(|e| format!("Save error: {}", e))?;
+            archive.remove(id).map_err(|e| format!("Save error: {}", e))?;
+            println!("[*] Restored #{} from archive.", id);
+            Ok(())
+        })();
+        if let Err(msg) = result {
+            eprintln!("Error: {}", msg);
+            exit(1);
+        }
+        return;
+    }
+
+    let store = match &cli.cmd {
+        Commands::List { archived: true, .. } => "finished",
+        _ => "tasks",
+    };
+    let mut repo = match open_repository(backend, store) {
+        Ok(r) => r,
         Err(e) => {
-            eprintln!("Error loading tasks: {}", e);
+            eprintln!("Error opening storage: {}", e);
             exit(1);
         }
     };
 
     let result = match cli.cmd {
-        Commands::Add { description } => {
+        Commands::Add {
+            description,
+            priority,
+            project,
+            link,
+            due,
+        } => {
             let desc = description.join(" ");
-            let id = next_id(&tasks);
-            tasks.push(Task { id, description: desc.clone(), done: false });
-            save_tasks(&tasks)
-                .map_err(|e| format!("Save error: {}", e))
-                .map(|_| println!("[+] Added #{}: {}", id, desc))
-        }
-        Commands::List => {
-            if tasks.is_empty() {
+            repo.insert(InsertTaskData {
+                description: desc.clone(),
+                priority,
+                project,
+                link,
+                due,
+            })
+            .map_err(|e| format!("Save error: {}", e))
+            .map(|t| println!("[+] Added #{}: {}", t.id, t.description))
+        }
+        Commands::List { project, sort, archived: _ } => match repo.all() {
+            Ok(tasks) if tasks.is_empty() => {
                 println!("No tasks found.");
                 Ok(())
-            } else {
-                for t in &tasks {
-                    let mark = if t.done { "[x]" } else { "[ ]" };
-                    println!("{} {}: {}", mark, t.id, t.description);
+            }
+            Ok(mut tasks) => {
+                sort_tasks(&mut tasks, sort);
+                if let Some(wanted) = project {
+                    for t in tasks.iter().filter(|t| t.project.as_deref() == Some(wanted.as_str())) {
+                        print_task(t);
+                    }
+                } else {
+                    print_grouped_by_project(&tasks);
                 }
                 Ok(())
             }
-        }
-        Commands::Done { id } => {
-            if let Some(t) = tasks.iter_mut().find(|t| t.id == id) {
-                t.done = true;
-                save_tasks(&tasks)
-                    .map_err(|e| format!("Save error: {}", e))
-                    .map(|_| println!("[✓] Marked #{} done.", id))
-            } else {
-                Err(format!("Task #{} not found.", id))
-            }
-        }
-        Commands::Remove { id } => {
-            let original = tasks.len();
-            tasks.retain(|t| t.id != id);
-            if tasks.len() < original {
-                save_tasks(&tasks)
-                    .map_err(|e| format!("Save error: {}", e))
-                    .map(|_| println!("[-] Removed #{}.", id))
-            } else {
-                Err(format!("Task #{} not found.", id))
-            }
-        }
-        Commands::Clear => {
-            tasks.clear();
-            save_tasks(&tasks)
-                .map_err(|e| format!("Save error: {}", e))
-                .map(|_| println!("[!] All tasks cleared."))
+            Err(e) => Err(format!("Load error: {}", e)),
+        },
+        Commands::Done { id } => repo
+            .update(id, UpdateTaskData { done: Some(true), ..Default::default() })
+            .map_err(|e| format!("Save error: {}", e))
+            .and_then(|found| {
+                if found {
+                    println!("[✓] Marked #{} done.", id);
+                    Ok(())
+                } else {
+                    Err(format!("Task #{} not found.", id))
+                }
+            }),
+        Commands::Due { id, date } => repo
+            .update(
+                id,
+                UpdateTaskData {
+                    due: Some(date),
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| format!("Save error: {}", e))
+            .and_then(|found| {
+                if found {
+                    println!("[*] Task #{} due date set to {}.", id, date);
+                    Ok(())
+                } else {
+                    Err(format!("Task #{} not found.", id))
+                }
+            }),
+        Commands::Priority { id, level } => repo
+            .update(
+                id,
+                UpdateTaskData {
+                    priority: Some(level),
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| format!("Save error: {}", e))
+            .and_then(|found| {
+                if found {
+                    println!("[*] Task #{} priority set to {}.", id, level);
+                    Ok(())
+                } else {
+                    Err(format!("Task #{} not found.", id))
+                }
+            }),
+        Commands::Remove { id } => repo
+            .remove(id)
+            .map_err(|e| format!("Save error: {}", e))
+            .and_then(|found| {
+                if found {
+                    println!("[-] Removed #{}.", id);
+                    Ok(())
+                } else {
+                    Err(format!("Task #{} not found.", id))
+                }
+            }),
+        Commands::Clear => repo
+            .clear()
+            .map_err(|e| format!("Save error: {}", e))
+            .map(|_| println!("[!] All tasks cleared.")),
+        Commands::Migrate | Commands::Archive | Commands::Restore { .. } | Commands::Completions { .. } => {
+            unreachable!("handled above")
         }
     };
 